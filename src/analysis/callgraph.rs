@@ -0,0 +1,113 @@
+//! A whole-program call graph over mono `Instance`s, built by scanning each
+//! instance's optimized MIR for `Call` terminators.
+//!
+//! Building the graph is split into a parallel phase and a serial phase:
+//! per-instance summaries (which callees a given instance's MIR calls) are
+//! computed independently of one another, reading only through the
+//! thread-safe query system, via `rustc_data_structures::sync`'s
+//! rustc-rayon-backed parallel iteration. Nothing in that phase touches
+//! `self`. The summaries are then folded into the single `CallGraph` one at
+//! a time on the calling thread, since `petgraph::DiGraph` is not `Sync`
+//! and node/edge insertion order would otherwise be a data race.
+
+use petgraph::graph::{DiGraph, NodeIndex};
+use rustc_data_structures::fx::FxHashMap;
+use rustc_data_structures::sync::par_for_each_in;
+use rustc_hir::def_id::DefId;
+use rustc_middle::mir::TerminatorKind;
+use rustc_middle::ty::{Instance, InstanceDef, ParamEnv, TyCtxt};
+use std::sync::Mutex;
+
+pub struct CallGraph<'tcx> {
+    pub graph: DiGraph<Instance<'tcx>, ()>,
+    nodes: FxHashMap<Instance<'tcx>, NodeIndex>,
+}
+
+/// The result of analyzing one instance's MIR in isolation: who it calls.
+/// Computed in the parallel phase, consumed in the serial fold.
+struct InstanceSummary<'tcx> {
+    instance: Instance<'tcx>,
+    callees: Vec<Instance<'tcx>>,
+}
+
+impl<'tcx> CallGraph<'tcx> {
+    pub fn new() -> Self {
+        Self {
+            graph: DiGraph::new(),
+            nodes: FxHashMap::default(),
+        }
+    }
+
+    fn node(&mut self, instance: Instance<'tcx>) -> NodeIndex {
+        *self
+            .nodes
+            .entry(instance)
+            .or_insert_with(|| self.graph.add_node(instance))
+    }
+
+    /// Populate the graph: one node per mono `Instance`, one edge per direct
+    /// call site resolved through `Instance::resolve`. Per-instance summaries
+    /// are computed in parallel (see [`InstanceSummary`]); only the fold
+    /// into `self.graph` is serial.
+    pub fn analyze(
+        &mut self,
+        instances: Vec<Instance<'tcx>>,
+        tcx: TyCtxt<'tcx>,
+        param_env: ParamEnv<'tcx>,
+    ) {
+        let summaries = Mutex::new(Vec::with_capacity(instances.len()));
+        par_for_each_in(instances, |instance| {
+            let summary = summarize_instance(tcx, param_env, instance);
+            summaries.lock().unwrap().push(summary);
+        });
+
+        for summary in summaries.into_inner().unwrap() {
+            let caller = self.node(summary.instance);
+            for callee in summary.callees {
+                let callee_node = self.node(callee);
+                self.graph.add_edge(caller, callee_node, ());
+            }
+        }
+    }
+}
+
+/// Read-only: scans `instance`'s MIR for `Call` terminators and resolves
+/// each callee. Safe to run concurrently across instances since it only
+/// reads through `tcx`'s thread-safe query system and never touches a
+/// `CallGraph`.
+fn summarize_instance<'tcx>(
+    tcx: TyCtxt<'tcx>,
+    param_env: ParamEnv<'tcx>,
+    instance: Instance<'tcx>,
+) -> InstanceSummary<'tcx> {
+    let mut callees = Vec::new();
+    let def_id = match instance.def {
+        InstanceDef::Item(def) => def.did,
+        _ => return InstanceSummary { instance, callees },
+    };
+    if !tcx.is_mir_available(def_id) {
+        return InstanceSummary { instance, callees };
+    }
+    let body = tcx.instance_mir(instance.def);
+    for bb in body.basic_blocks.iter() {
+        let Some(terminator) = &bb.terminator else { continue };
+        if let TerminatorKind::Call { func, .. } = &terminator.kind {
+            let Some((callee_def_id, substs)) = func.const_fn_def() else {
+                continue;
+            };
+            if let Some(callee) = resolve_callee(tcx, param_env, callee_def_id, substs) {
+                callees.push(callee);
+            }
+        }
+    }
+    InstanceSummary { instance, callees }
+}
+
+fn resolve_callee<'tcx>(
+    tcx: TyCtxt<'tcx>,
+    param_env: ParamEnv<'tcx>,
+    def_id: DefId,
+    substs: rustc_middle::ty::SubstsRef<'tcx>,
+) -> Option<Instance<'tcx>> {
+    Instance::resolve(tcx, param_env, def_id, substs).ok().flatten()
+}
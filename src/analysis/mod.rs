@@ -0,0 +1,4 @@
+//! Whole-program analyses built on top of the mono `Instance`s rustc
+//! collected for codegen.
+
+pub mod callgraph;
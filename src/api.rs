@@ -0,0 +1,94 @@
+//! Programmatic entrypoint: analyze a source string directly, without a
+//! cargo project on disk or a `rustc` subprocess.
+//!
+//! This is the same trick rustdoc's doctest harness uses to compile a
+//! snippet in-process (`rustc_interface::interface::run_compiler` over an
+//! in-memory source string rather than a file on disk) rather than the full cargo-driven
+//! plugin path `LockBudCallbacks` normally runs under. Codegen is disabled
+//! (same reasoning as the test harness: LLVM isn't thread safe, and callers
+//! of this API — a playground backend, an IDE server, a fuzzer — only want
+//! the reports, not an artifact).
+
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::callbacks::LockBudCallbacks;
+use crate::detector::lock::Report;
+use crate::options::Options;
+
+/// What kind of crate the source should be parsed as. Mirrors the subset of
+/// `--crate-type` values that make sense for a single in-memory snippet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CrateType {
+    Lib,
+    Bin,
+}
+
+impl CrateType {
+    fn as_flag(self) -> &'static str {
+        match self {
+            CrateType::Lib => "lib",
+            CrateType::Bin => "bin",
+        }
+    }
+}
+
+/// Analyze `source` and return whatever `Report`s the deadlock detector
+/// finds. `edition` is a string like `"2021"`, matching `--edition`.
+pub fn analyze_str(source: &str, crate_type: CrateType, edition: &str, mut options: Options) -> Vec<Report> {
+    let threads = options.threads;
+    // Force non-incremental regardless of what the caller asked for. The
+    // sidecar cache is keyed on (output_directory, crate name, StableCrateId)
+    // under the assumption that those together identify one real, stable
+    // crate across runs; a snippet compiled from a string has none of
+    // that — no `--crate-name` is passed, so rustc falls back to the same
+    // inferred name for every call, and repeated calls from a playground
+    // backend or a fuzzer have no stable on-disk identity to invalidate
+    // against anyway. Serving one snippet's cached reports for another's
+    // MIR would be silently wrong, not just stale.
+    options.incremental = false;
+    let mut callbacks = LockBudCallbacks::new_for_test(options);
+    // No `-Zno-codegen` here: `analyze_with_lockbud` itself bails out early
+    // when `no_codegen`/`!should_codegen()` is set, which would make this
+    // always return an empty `Vec`. Codegen is instead skipped by
+    // `new_for_test`'s `Compilation::Stop`, same as the fixture harness.
+    let mut args = vec![
+        "lockbud-api".to_string(),
+        format!("--crate-type={}", crate_type.as_flag()),
+        format!("--edition={edition}"),
+    ];
+    // `Options::threads` can only reach rustc's own `rustc_rayon` pool (the
+    // one `par_for_each_in` actually dispatches on) by being set before the
+    // `Session` is built, i.e. as `-Z threads` on the args we construct here
+    // — by the time `LockBudCallbacks::after_analysis` runs, that pool
+    // already has its final size.
+    if let Some(threads) = threads {
+        args.push(format!("-Zthreads={threads}"));
+    }
+    // A virtual file name whose contents `SourceStringLoader` serves from
+    // memory. `-` would not do: rustc reads a `-` input from the process's
+    // stdin itself, without going through the file loader.
+    args.push("lockbud_snippet.rs".to_string());
+    let _ = rustc_driver::catch_fatal_errors(|| {
+        rustc_driver::RunCompiler::new(&args, &mut callbacks)
+            .set_file_loader(Some(Box::new(SourceStringLoader { source: source.to_string() })))
+            .run()
+    });
+    callbacks.reports().map(<[Report]>::to_vec).unwrap_or_default()
+}
+
+struct SourceStringLoader {
+    source: String,
+}
+
+impl rustc_span::source_map::FileLoader for SourceStringLoader {
+    fn file_exists(&self, _path: &Path) -> bool {
+        true
+    }
+    fn read_file(&self, _path: &Path) -> std::io::Result<String> {
+        Ok(self.source.clone())
+    }
+    fn read_binary_file(&self, path: &Path) -> std::io::Result<Arc<[u8]>> {
+        Ok(Arc::from(self.read_file(path)?.into_bytes()))
+    }
+}
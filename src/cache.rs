@@ -0,0 +1,230 @@
+//! Sidecar cache of per-function lock summaries, so a second `cargo check`
+//! after a small edit doesn't have to re-run the detector over every
+//! function again — only over the ones whose MIR, or a *transitive*
+//! callee's MIR, actually changed since the last run.
+//!
+//! Borrows cargo's global-cache-tracker idea: each function gets a stable
+//! key (its crate's `StableCrateId` + its `DefPathHash`) and a fingerprint
+//! of its optimized MIR. On a cache hit the stored `Report`s are reused
+//! as-is (their spans are re-resolved against the current `Body`, see
+//! [`crate::detector::lock::Report::rehydrate`]); on a miss, the function
+//! is re-analyzed and the entry is refreshed. Reusing a summary is exactly
+//! as parallel as computing one from scratch: both happen inside the same
+//! `rustc_data_structures::sync::par_for_each_in` pass, so caching doesn't
+//! regress chunk0-3's parallelization back to a serial walk.
+//!
+//! The sidecar file itself is also keyed on the crate's `StableCrateId`, not
+//! just its name: two crates that happen to share a name (different
+//! versions of a dependency in a workspace, or two unrelated ad hoc
+//! compilations) must never read or write each other's cache file. Each run
+//! also rewrites the file with exactly the keys it touched, so an entry for
+//! a function that's since been deleted or edited doesn't linger forever.
+
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use rustc_data_structures::fx::FxHashMap;
+use rustc_data_structures::sync::par_for_each_in;
+use rustc_middle::ty::{Instance, TyCtxt};
+use serde::{Deserialize, Serialize};
+
+use crate::analysis::callgraph::CallGraph;
+use crate::detector::lock::{self, DeadlockDetector, Report};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+struct FunctionKey {
+    stable_crate_id: u64,
+    def_path_hash: u64,
+    /// Fixpoint fingerprint: folds in this function's own MIR hash and
+    /// every *transitive* callee's own-MIR hash (see
+    /// [`transitive_fingerprints`]), so an edit anywhere in the call tree
+    /// invalidates every caller, not just the direct one.
+    fingerprint: u64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CacheFile {
+    entries: Vec<(FunctionKey, Vec<Report>)>,
+}
+
+pub struct SummaryCache {
+    path: PathBuf,
+    entries: FxHashMap<FunctionKey, Vec<Report>>,
+}
+
+impl SummaryCache {
+    /// Load the sidecar cache for this crate out of `output_directory`, or
+    /// start empty if it doesn't exist yet or fails to parse (e.g. written
+    /// by an older, incompatible version of lockbud). The file is keyed on
+    /// `stable_crate_id`, not just `crate_name`: two distinct crates (e.g.
+    /// different versions of the same-named dependency in a workspace, or
+    /// two unrelated ad hoc compilations that both ended up with the
+    /// inferred name `rust_out`) must never be able to collide on the same
+    /// cache file and serve each other's reports.
+    pub fn load(output_directory: &Path, crate_name: &str, stable_crate_id: u64) -> Self {
+        let path = cache_path(output_directory, crate_name, stable_crate_id);
+        let entries = fs::read_to_string(&path)
+            .ok()
+            .and_then(|s| serde_json::from_str::<CacheFile>(&s).ok())
+            .map(|f| f.entries.into_iter().collect())
+            .unwrap_or_default();
+        Self { path, entries }
+    }
+
+    /// Overwrite the on-disk cache with exactly `entries` — every key this
+    /// run actually touched, whether reused from a cache hit or freshly
+    /// computed. Anything left over from a previous run that wasn't
+    /// touched (a deleted function, or one whose fingerprint moved on) is
+    /// dropped instead of accumulating in the file forever.
+    fn save(&self, entries: FxHashMap<FunctionKey, Vec<Report>>) {
+        let file = CacheFile { entries: entries.into_iter().collect() };
+        if let Ok(json) = serde_json::to_string(&file) {
+            let _ = fs::create_dir_all(self.path.parent().unwrap());
+            let _ = fs::write(&self.path, json);
+        }
+    }
+}
+
+fn cache_path(output_directory: &Path, crate_name: &str, stable_crate_id: u64) -> PathBuf {
+    output_directory
+        .join("lockbud-cache")
+        .join(format!("{crate_name}-{stable_crate_id:016x}.lock-summary.json"))
+}
+
+fn hash_body(body: &rustc_middle::mir::Body<'_>) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    // `Body`'s `Debug` output captures every statement/terminator, so it's a
+    // cheap (if coarse) stand-in for a real `HashStable` fingerprint.
+    format!("{body:?}").hash(&mut hasher);
+    hasher.finish()
+}
+
+fn fold(own: u64, mut callee_fingerprints: Vec<u64>) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    own.hash(&mut hasher);
+    callee_fingerprints.sort_unstable();
+    callee_fingerprints.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Compute, for every instance with a MIR body, a fingerprint that depends
+/// on its own MIR *and* every function transitively reachable from it.
+///
+/// This is a fixpoint over the callgraph rather than a single direct-callee
+/// fold: relaxing `combined[f] = fold(own[f], combined[callees of f])` one
+/// round only propagates a change one call-edge per round, same as
+/// Bellman-Ford's relaxation. Iterating `node_count` rounds is enough for
+/// the longest possible call chain to have propagated end to end, and the
+/// loop still exits early once nothing changes (the common case: most
+/// functions settle in far fewer rounds than the graph's longest path).
+fn transitive_fingerprints<'tcx>(
+    tcx: TyCtxt<'tcx>,
+    callgraph: &CallGraph<'tcx>,
+) -> FxHashMap<Instance<'tcx>, u64> {
+    let mut own_hashes: FxHashMap<Instance<'tcx>, u64> = FxHashMap::default();
+    for node in callgraph.graph.node_indices() {
+        let instance = callgraph.graph[node];
+        if tcx.is_mir_available(instance.def_id()) {
+            own_hashes.insert(instance, hash_body(tcx.instance_mir(instance.def)));
+        }
+    }
+    let mut combined = own_hashes.clone();
+
+    let rounds = callgraph.graph.node_count().max(1);
+    for _ in 0..rounds {
+        let mut changed = false;
+        let mut next = FxHashMap::default();
+        for node in callgraph.graph.node_indices() {
+            let instance = callgraph.graph[node];
+            let Some(&own) = own_hashes.get(&instance) else { continue };
+            let callee_fingerprints: Vec<u64> = callgraph
+                .graph
+                .neighbors(node)
+                .filter_map(|n| combined.get(&callgraph.graph[n]).copied())
+                .collect();
+            let folded = fold(own, callee_fingerprints);
+            if combined.get(&instance) != Some(&folded) {
+                changed = true;
+            }
+            next.insert(instance, folded);
+        }
+        combined = next;
+        if !changed {
+            break;
+        }
+    }
+    combined
+}
+
+fn function_key(tcx: TyCtxt<'_>, instance: Instance<'_>, fingerprint: u64) -> FunctionKey {
+    let def_path_hash = tcx.def_path_hash(instance.def_id());
+    FunctionKey {
+        stable_crate_id: def_path_hash.stable_crate_id().to_u64(),
+        def_path_hash: def_path_hash.local_hash().as_u64(),
+        fingerprint,
+    }
+}
+
+/// Run deadlock detection over `callgraph`, reusing cached per-function
+/// `DoubleLock` reports for functions whose transitive fingerprint didn't
+/// change since the cache at `output_directory` was last written.
+/// `ConflictLock`s depend on every function's lock-acquisition edges at
+/// once, so those are always recomputed (cheaply: it's a single MIR scan,
+/// not the pairwise analysis the cache is built to skip) and folded in
+/// serially after the parallel per-function pass.
+pub fn incremental_detect<'tcx>(
+    tcx: TyCtxt<'tcx>,
+    callgraph: &CallGraph<'tcx>,
+    output_directory: &Path,
+    crate_name: &str,
+) -> Vec<Report> {
+    let stable_crate_id = tcx.stable_crate_id(rustc_hir::def_id::LOCAL_CRATE).to_u64();
+    let cache = SummaryCache::load(output_directory, crate_name, stable_crate_id);
+    let fingerprints = transitive_fingerprints(tcx, callgraph);
+
+    let instances: Vec<_> = callgraph.graph.node_indices().map(|n| callgraph.graph[n]).collect();
+    let next_entries: Mutex<FxHashMap<FunctionKey, Vec<Report>>> = Mutex::new(FxHashMap::default());
+    let collected: Mutex<Vec<(Vec<Report>, Vec<lock::LockEdge>)>> = Mutex::new(Vec::new());
+
+    par_for_each_in(instances, |instance| {
+        let Some(&fingerprint) = fingerprints.get(&instance) else { return };
+        let body = tcx.instance_mir(instance.def);
+        let key = function_key(tcx, instance, fingerprint);
+
+        let doubles = match cache.entries.get(&key) {
+            Some(cached) => {
+                let mut cached = cached.clone();
+                for report in &mut cached {
+                    report.rehydrate(body);
+                }
+                next_entries.lock().unwrap().insert(key, cached.clone());
+                cached
+            }
+            None => {
+                let found = DeadlockDetector::detect_function(tcx, instance, body);
+                next_entries.lock().unwrap().insert(key, found.clone());
+                found
+            }
+        };
+
+        let guards = lock::guard_sites(tcx, body);
+        let edges = lock::lock_edges(tcx, instance, body, &guards);
+        if !doubles.is_empty() || !edges.is_empty() {
+            collected.lock().unwrap().push((doubles, edges));
+        }
+    });
+
+    cache.save(next_entries.into_inner().unwrap());
+
+    let mut reports = Vec::new();
+    let mut all_edges = Vec::new();
+    for (doubles, edges) in collected.into_inner().unwrap() {
+        reports.extend(doubles);
+        all_edges.extend(edges);
+    }
+    reports.extend(lock::detect_conflicts(&all_edges));
+    reports
+}
@@ -5,7 +5,9 @@ extern crate rustc_hir;
 
 use std::path::PathBuf;
 
+use crate::cache;
 use crate::cs;
+use crate::diagnostics;
 use crate::options::{CrateNameList, DetectorKind, Options};
 use log::{debug, warn};
 use rustc_driver::Compilation;
@@ -24,6 +26,7 @@ pub struct LockBudCallbacks {
     file_name: String,
     output_directory: PathBuf,
     test_run: bool,
+    captured_reports: Option<Vec<Report>>,
 }
 
 impl LockBudCallbacks {
@@ -33,8 +36,27 @@ impl LockBudCallbacks {
             file_name: String::new(),
             output_directory: PathBuf::default(),
             test_run: false,
+            captured_reports: None,
         }
     }
+
+    /// Like [`Self::new`], but skips codegen after analysis (LLVM is not
+    /// used in a thread safe manner, so the regression harness can't share
+    /// it across the many fixtures it drives through a single process) and
+    /// keeps the resulting reports around for inspection instead of just
+    /// logging them.
+    pub fn new_for_test(options: Options) -> Self {
+        Self {
+            test_run: true,
+            ..Self::new(options)
+        }
+    }
+
+    /// The reports produced by the most recent `after_analysis`, if any.
+    /// Only meaningful when constructed with [`Self::new_for_test`].
+    pub fn reports(&self) -> Option<&[Report]> {
+        self.captured_reports.as_deref()
+    }
 }
 
 impl rustc_driver::Callbacks for LockBudCallbacks {
@@ -74,6 +96,12 @@ impl rustc_driver::Callbacks for LockBudCallbacks {
             .peek_mut()
             .enter(|tcx| {
                 let reports = self.analyze_with_lockbud(compiler, tcx);
+                if let Some(reports) = &reports {
+                    diagnostics::emit_reports(compiler, reports, self.options.diagnostic_format);
+                }
+                if self.test_run {
+                    self.captured_reports = reports.clone();
+                }
                 cs::analyze(tcx, reports).unwrap();
         });
         if self.test_run {
@@ -113,20 +141,33 @@ impl LockBudCallbacks {
                 })
             })
             .collect();
-        let mut callgraph = CallGraph::new();
         let param_env = ParamEnv::reveal_all();
+        let incremental = self.options.incremental;
+        let output_directory = self.output_directory.clone();
+        // Callgraph construction and lock detection parallelize through
+        // `rustc_data_structures::sync::par_for_each_in`, which dispatches on
+        // rustc's own `rustc_rayon` thread pool, not the `rayon` crate — that
+        // pool's size is fixed by `-Z threads` when the `Session` is built,
+        // long before `after_analysis` runs. `Options::threads` therefore
+        // can't be applied here; a caller who builds the `rustc_driver` args
+        // itself (like `api::analyze_str`) is the one place that can still
+        // honor it, by passing it through as `-Z threads`.
+        let mut callgraph = CallGraph::new();
         callgraph.analyze(instances.clone(), tcx, param_env);
-        match self.options.detector_kind {
+        let reports = match self.options.detector_kind {
+            DetectorKind::Deadlock if incremental => {
+                cache::incremental_detect(tcx, &callgraph, &output_directory, &crate_name)
+            }
             DetectorKind::Deadlock => {
-                let mut deadlock_detector = DeadlockDetector::new(tcx, param_env);
-                let reports = deadlock_detector.detect(&callgraph);
-                if !reports.is_empty() {
-                    let j = serde_json::to_string_pretty(&reports).unwrap();
-                    warn!("{}", j);
-                    report_stats(&crate_name, &reports);
-                    return Some(reports)
-                }
+                let mut deadlock_detector = DeadlockDetector::new(tcx);
+                deadlock_detector.detect(&callgraph)
             }
+        };
+        if !reports.is_empty() {
+            let j = serde_json::to_string_pretty(&reports).unwrap();
+            warn!("{}", j);
+            report_stats(&crate_name, &reports);
+            return Some(reports)
         }
 
         None
@@ -0,0 +1,34 @@
+//! Crate-summary bookkeeping: lockbud is typically pointed at a large corpus
+//! of crates, one rustc invocation at a time, so this module records a
+//! one-line summary per crate (how many reports it produced, or that it was
+//! skipped or produced none) to the shared corpus log rather than just to stdout.
+
+use std::fmt::Write as _;
+use std::io::Write as _;
+
+use rustc_hir::def_id::LOCAL_CRATE;
+use rustc_middle::ty::TyCtxt;
+
+use crate::detector::lock::Report;
+
+/// Append a one-line summary for the current crate to `lockbud.summary.log`
+/// in the working directory. `reports` is `None` when the crate was skipped
+/// (e.g. filtered out by `CrateNameList` or not code-genned at all) and also
+/// when it was analyzed but produced no reports; both are logged alike.
+pub fn analyze(tcx: TyCtxt<'_>, reports: Option<Vec<Report>>) -> std::io::Result<()> {
+    let crate_name = tcx.crate_name(LOCAL_CRATE).to_string();
+    let mut line = String::new();
+    match &reports {
+        None => {
+            let _ = write!(line, "{crate_name}: skipped or no reports");
+        }
+        Some(reports) => {
+            let _ = write!(line, "{crate_name}: {} reports", reports.len());
+        }
+    }
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open("lockbud.summary.log")?;
+    writeln!(file, "{line}")
+}
@@ -0,0 +1,404 @@
+//! Deadlock detection over a whole-program call graph.
+//!
+//! Two bug shapes are reported:
+//!
+//! - `DoubleLock`: a thread acquires a lock guard while it may already be
+//!   holding a guard for the *same* lock (reentrant `Mutex::lock`, etc.).
+//! - `ConflictLock`: two guards for *different* locks are acquired in
+//!   inconsistent orders on different paths, which can deadlock against
+//!   another thread that acquires them in the opposite order.
+//!
+//! Guards are recognized structurally, by matching the *call* that produces
+//! them against known lock method names (`lock`/`read`/`write`/...) on a
+//! `Mutex`/`RwLock` receiver, rather than by a fixed set of `DefId`s, since
+//! those methods are generic (`Mutex::<T>::lock`) over arbitrary `T`.
+//!
+//! `DoubleLock`s are `"Probably"` when both acquisitions are in the same
+//! basic block (no intervening branch can make them mutually exclusive) and
+//! `"Possibly"` otherwise; `ConflictLock`s are always `"Possibly"`, since
+//! whether two functions actually race depends on concurrent execution that
+//! a single-crate static analysis can't confirm.
+
+use std::sync::Mutex;
+
+use rustc_data_structures::sync::par_for_each_in;
+use rustc_middle::mir::{BasicBlock, Location};
+use rustc_middle::ty::{Instance, TyCtxt};
+use rustc_span::{Span, DUMMY_SP};
+use serde::{Deserialize, Serialize};
+
+use crate::analysis::callgraph::CallGraph;
+
+fn dummy_span() -> Span {
+    DUMMY_SP
+}
+
+/// A single lock-guard creation site. `block`/`statement_index` are the
+/// serializable, cache-friendly form of the MIR `Location`; `span` is
+/// derived from them (via `Body::source_info`) and is not itself
+/// serialized, since it is only ever meaningful alongside the `Body` it was
+/// resolved from. `line` *is* serialized: unlike `Span`, which is only
+/// resolvable against the `SourceMap` of the compiler session that created
+/// it, a 1-based source line is a plain number and survives being written
+/// to the incremental cache or a golden `.expected.json` file and read back
+/// in a later, unrelated session.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LockSite {
+    pub func: String,
+    pub block: u32,
+    pub statement_index: usize,
+    pub line: usize,
+    #[serde(skip, default = "dummy_span")]
+    pub span: Span,
+}
+
+impl LockSite {
+    pub fn location(&self) -> Location {
+        Location {
+            block: BasicBlock::from_u32(self.block),
+            statement_index: self.statement_index,
+        }
+    }
+
+    /// Re-resolve `span` from `body`. Only valid when `body` is the same
+    /// MIR this site was originally found in (e.g. a cache hit, where the
+    /// function's fingerprint confirmed the MIR hasn't changed).
+    pub fn rehydrate(&mut self, body: &rustc_middle::mir::Body<'_>) {
+        self.span = body.source_info(self.location()).span;
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DoubleLockReport {
+    pub possibility: String,
+    pub first_lock: LockSite,
+    pub second_lock: LockSite,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConflictLockReport {
+    pub possibility: String,
+    pub first_lock: LockSite,
+    pub second_lock: LockSite,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum Report {
+    DoubleLock(DoubleLockReport),
+    ConflictLock(ConflictLockReport),
+}
+
+impl Report {
+    /// The second (offending) acquisition: where the diagnostic should
+    /// point its primary span.
+    pub fn primary_site(&self) -> &LockSite {
+        match self {
+            Report::DoubleLock(r) => &r.second_lock,
+            Report::ConflictLock(r) => &r.second_lock,
+        }
+    }
+
+    /// The first acquisition: shown as a secondary, labeled note.
+    pub fn secondary_site(&self) -> &LockSite {
+        match self {
+            Report::DoubleLock(r) => &r.first_lock,
+            Report::ConflictLock(r) => &r.first_lock,
+        }
+    }
+
+    pub fn kind_str(&self) -> &'static str {
+        match self {
+            Report::DoubleLock(_) => "double lock",
+            Report::ConflictLock(_) => "conflicting lock order",
+        }
+    }
+
+    /// Re-resolve both sites' spans against `body` (see
+    /// [`LockSite::rehydrate`]). Used after loading a report back out of the
+    /// incremental cache.
+    pub fn rehydrate(&mut self, body: &rustc_middle::mir::Body<'_>) {
+        match self {
+            Report::DoubleLock(r) => {
+                r.first_lock.rehydrate(body);
+                r.second_lock.rehydrate(body);
+            }
+            Report::ConflictLock(r) => {
+                r.first_lock.rehydrate(body);
+                r.second_lock.rehydrate(body);
+            }
+        }
+    }
+}
+
+/// One lock acquisition found in a function's MIR: which lock it's on (a
+/// best-effort textual key for the receiver operand of the `lock`/`read`/
+/// `write` call, so two acquisitions of the *same* `Mutex` compare equal)
+/// and where.
+pub(crate) struct GuardSite {
+    lock_key: String,
+    location: Location,
+}
+
+/// One function's consecutive pair of acquisitions of two *different*
+/// locks, in program order: `before` is locked, then `after` is the next
+/// lock acquired; whether `before`'s guard is still held at that point is
+/// not checked. Used to cross-check against another
+/// function's edges for the opposite order (see [`detect_conflicts`]).
+pub struct LockEdge {
+    before_key: String,
+    after_key: String,
+    after_site: LockSite,
+}
+
+const LOCK_METHOD_NAMES: &[&str] = &["lock", "read", "write", "try_lock", "try_read", "try_write"];
+
+/// Does `def_id` look like `Mutex::lock`, `RwLock::write`, etc.? Matched on
+/// the resolved method's path rather than a fixed `DefId` list so this
+/// follows `parking_lot`/`std` alike, and any wrapper that re-exports the
+/// same method names.
+fn is_lock_method(tcx: TyCtxt<'_>, def_id: rustc_hir::def_id::DefId) -> bool {
+    let path = tcx.def_path_str(def_id);
+    let method = path.rsplit("::").next().unwrap_or("");
+    LOCK_METHOD_NAMES.contains(&method) && (path.contains("Mutex") || path.contains("RwLock"))
+}
+
+/// Scan `body` for calls to a lock method, recording the guard's creation
+/// site and a key identifying which lock was acquired (the receiver's root
+/// local, see [`root_local`]).
+pub(crate) fn guard_sites<'tcx>(tcx: TyCtxt<'tcx>, body: &rustc_middle::mir::Body<'tcx>) -> Vec<GuardSite> {
+    use rustc_middle::mir::TerminatorKind;
+    let mut guards = Vec::new();
+    for (bb, data) in body.basic_blocks.iter_enumerated() {
+        let Some(terminator) = &data.terminator else { continue };
+        if let TerminatorKind::Call { func, args, .. } = &terminator.kind {
+            let Some((def_id, _substs)) = func.const_fn_def() else { continue };
+            if !is_lock_method(tcx, def_id) {
+                continue;
+            }
+            let location = Location { block: bb, statement_index: data.statements.len() };
+            let lock_key = args
+                .get(0)
+                .and_then(operand_place)
+                .map(|place| format!("_{}", root_local(body, location, place.local).as_usize()))
+                .unwrap_or_default();
+            guards.push(GuardSite { lock_key, location });
+        }
+    }
+    guards
+}
+
+fn operand_place<'tcx>(op: &rustc_middle::mir::Operand<'tcx>) -> Option<rustc_middle::mir::Place<'tcx>> {
+    use rustc_middle::mir::Operand;
+    match op {
+        Operand::Copy(place) | Operand::Move(place) => Some(*place),
+        Operand::Constant(_) => None,
+    }
+}
+
+/// Trace a place's local back through the chain of trivial reborrows and
+/// reassignments (`_n = move _m`, `_n = &_m`, `_n = &mut _m`, ...) that
+/// autoref inserts ahead of a method call, to the local that actually owns
+/// the value — e.g. the `let m = Mutex::new(..)` binding itself, rather
+/// than a fresh per-call temporary.
+///
+/// Without this, every `m.lock()` call site gets its *own* receiver temp
+/// (`move _5`, `move _9`, ...), so keying on the operand's `Debug` text (or
+/// even its bare local) would never consider two acquisitions of the same
+/// owned lock to be the same lock. Walking backward from the call, within
+/// the same basic block, undoes exactly that rewriting without needing a
+/// full dataflow pass; it's bounded so a pathological chain can't loop.
+fn root_local(body: &rustc_middle::mir::Body<'_>, location: Location, mut local: rustc_middle::mir::Local) -> rustc_middle::mir::Local {
+    use rustc_middle::mir::{Rvalue, StatementKind};
+    let stmts = &body.basic_blocks[location.block].statements;
+    let mut idx = location.statement_index.min(stmts.len());
+    let mut hops = stmts.len();
+    while idx > 0 && hops > 0 {
+        hops -= 1;
+        idx -= 1;
+        let StatementKind::Assign(assign) = &stmts[idx].kind else { continue };
+        let (place, rvalue) = &**assign;
+        if place.local != local || !place.projection.is_empty() {
+            continue;
+        }
+        let next = match rvalue {
+            Rvalue::Use(op) => operand_place(op).map(|p| p.local),
+            Rvalue::Ref(_, _, p) | Rvalue::AddressOf(_, p) => Some(p.local),
+            _ => None,
+        };
+        match next {
+            Some(next_local) => local = next_local,
+            None => break,
+        }
+    }
+    local
+}
+
+/// Adjacent pairs of *distinct* locks acquired in program order, e.g.
+/// `[a, b, a]` yields edges `a->b` and `b->a`.
+pub(crate) fn lock_edges<'tcx>(
+    tcx: TyCtxt<'tcx>,
+    instance: Instance<'tcx>,
+    body: &rustc_middle::mir::Body<'tcx>,
+    guards: &[GuardSite],
+) -> Vec<LockEdge> {
+    let func = tcx.def_path_str(instance.def_id());
+    let mut edges = Vec::new();
+    for window in guards.windows(2) {
+        let [before, after] = window else { continue };
+        if before.lock_key == after.lock_key {
+            continue;
+        }
+        edges.push(LockEdge {
+            before_key: before.lock_key.clone(),
+            after_key: after.lock_key.clone(),
+            after_site: site(tcx, &func, body, after.location),
+        });
+    }
+    edges
+}
+
+/// Cross-check every pair of edges from *different* functions: if one
+/// function locks `a` then `b` while another locks `b` then `a`, the two
+/// can deadlock against each other. Always `"Possibly"` — unlike a
+/// same-function `DoubleLock`, whether this actually happens depends on
+/// both functions running concurrently, which a single-crate static
+/// analysis can't rule in or out.
+///
+/// `edges` is gathered per-function by a parallel pass (see
+/// `DeadlockDetector::detect` and `cache::incremental_detect`), so its
+/// incoming order isn't reproducible between runs. Sorting it first means
+/// which of a matching pair ends up `first_lock` vs. `second_lock` is a
+/// function of the edges themselves, not of scheduling.
+pub fn detect_conflicts(edges: &[LockEdge]) -> Vec<Report> {
+    let mut edges: Vec<&LockEdge> = edges.iter().collect();
+    edges.sort_by(|a, b| {
+        (&a.after_site.func, a.after_site.line, &a.before_key, &a.after_key).cmp(&(
+            &b.after_site.func,
+            b.after_site.line,
+            &b.before_key,
+            &b.after_key,
+        ))
+    });
+    let mut reports = Vec::new();
+    for (i, a) in edges.iter().enumerate() {
+        for b in &edges[i + 1..] {
+            if a.before_key == b.after_key
+                && a.after_key == b.before_key
+                && a.after_site.func != b.after_site.func
+            {
+                reports.push(Report::ConflictLock(ConflictLockReport {
+                    possibility: "Possibly".to_string(),
+                    first_lock: a.after_site.clone(),
+                    second_lock: b.after_site.clone(),
+                }));
+            }
+        }
+    }
+    reports
+}
+
+/// Resolve a `LockSite`, including its `line`, while `tcx`'s session (and
+/// thus its `SourceMap`) is still alive. Resolving a line from a `Span`
+/// later, once the session that created the span is gone, isn't possible —
+/// a fresh, empty `SourceMap` has no file to look the position up in.
+fn site(tcx: TyCtxt<'_>, func: &str, body: &rustc_middle::mir::Body<'_>, location: Location) -> LockSite {
+    let span = body.source_info(location).span;
+    let line = tcx.sess.source_map().lookup_char_pos(span.lo()).line;
+    LockSite {
+        func: func.to_string(),
+        block: location.block.as_u32(),
+        statement_index: location.statement_index,
+        line,
+        span,
+    }
+}
+
+pub struct DeadlockDetector<'tcx> {
+    tcx: TyCtxt<'tcx>,
+}
+
+impl<'tcx> DeadlockDetector<'tcx> {
+    pub fn new(tcx: TyCtxt<'tcx>) -> Self {
+        Self { tcx }
+    }
+
+    /// Per-function `DoubleLock` detection and edge collection are each
+    /// independent of every other function, so they run in parallel over
+    /// the callgraph's nodes. Cross-function `ConflictLock` synthesis needs
+    /// every function's edges at once, so it runs as a serial fold
+    /// afterwards.
+    pub fn detect(&mut self, callgraph: &CallGraph<'tcx>) -> Vec<Report> {
+        let tcx = self.tcx;
+        let instances: Vec<_> = callgraph.graph.node_indices().map(|n| callgraph.graph[n]).collect();
+        let collected: Mutex<Vec<(Vec<Report>, Vec<LockEdge>)>> = Mutex::new(Vec::new());
+        par_for_each_in(instances, |instance| {
+            if !tcx.is_mir_available(instance.def_id()) {
+                return;
+            }
+            let body = tcx.instance_mir(instance.def);
+            let guards = guard_sites(tcx, body);
+            let doubles = detect_double_locks(tcx, instance, body, &guards);
+            let edges = lock_edges(tcx, instance, body, &guards);
+            if !doubles.is_empty() || !edges.is_empty() {
+                collected.lock().unwrap().push((doubles, edges));
+            }
+        });
+        let collected = collected.into_inner().unwrap();
+        let mut reports = Vec::new();
+        let mut all_edges = Vec::new();
+        for (doubles, edges) in collected {
+            reports.extend(doubles);
+            all_edges.extend(edges);
+        }
+        reports.extend(detect_conflicts(&all_edges));
+        reports
+    }
+
+    /// Detect `DoubleLock`s within a single function, independent of the
+    /// rest of the callgraph. This is the unit of work the incremental
+    /// cache (`crate::cache`) keys on: given the same `instance` and an
+    /// unchanged `Body`, it always returns the same reports. Cross-function
+    /// `ConflictLock`s aren't in scope here since they need every
+    /// function's edges; see [`lock_edges`] and [`detect_conflicts`].
+    pub fn detect_function(
+        tcx: TyCtxt<'tcx>,
+        instance: Instance<'tcx>,
+        body: &rustc_middle::mir::Body<'tcx>,
+    ) -> Vec<Report> {
+        let guards = guard_sites(tcx, body);
+        detect_double_locks(tcx, instance, body, &guards)
+    }
+}
+
+/// Report a `DoubleLock` for every pair of acquisitions that lock the
+/// *same* lock. `Probably` when both happen in the same basic block (no
+/// intervening branch, so the second is unconditionally reached once the
+/// first is); `Possibly` otherwise, since a branch may make them mutually
+/// exclusive at runtime even though both are reachable in the CFG.
+fn detect_double_locks<'tcx>(
+    tcx: TyCtxt<'tcx>,
+    instance: Instance<'tcx>,
+    body: &rustc_middle::mir::Body<'tcx>,
+    guards: &[GuardSite],
+) -> Vec<Report> {
+    let func = tcx.def_path_str(instance.def_id());
+    let mut reports = Vec::new();
+    for (i, first) in guards.iter().enumerate() {
+        for second in &guards[i + 1..] {
+            if first.lock_key != second.lock_key {
+                continue;
+            }
+            let possibility = if first.location.block == second.location.block {
+                "Probably"
+            } else {
+                "Possibly"
+            };
+            reports.push(Report::DoubleLock(DoubleLockReport {
+                possibility: possibility.to_string(),
+                first_lock: site(tcx, &func, body, first.location),
+                second_lock: site(tcx, &func, body, second.location),
+            }));
+        }
+    }
+    reports
+}
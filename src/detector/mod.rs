@@ -0,0 +1,3 @@
+//! Bug detectors that consume a [`crate::analysis::callgraph::CallGraph`].
+
+pub mod lock;
@@ -0,0 +1,81 @@
+//! Render `Report`s as rustc diagnostics instead of (or alongside) a raw
+//! JSON blob, so a deadlock shows up with the same source context and
+//! IDE/terminal integration as a normal compiler warning.
+//!
+//! The second (conflicting) lock acquisition is the primary span; the first
+//! acquisition is attached as a secondary, labeled span, mirroring how rustc
+//! itself points at a borrow-conflict and its originating borrow.
+
+use rustc_errors::emitter::{Emitter, EmitterWriter, HumanReadableErrorType};
+use rustc_errors::{ColorConfig, Handler};
+use rustc_interface::interface::Compiler;
+
+use crate::detector::lock::Report;
+use crate::options::DiagnosticFormat;
+
+/// Emit every report as a diagnostic.
+///
+/// `Json` prints one JSON object per report to stdout (so it can be piped
+/// into other tooling) and skips diagnostics entirely. `Human` reuses
+/// `compiler.session()`'s own handler, so it gets exactly the
+/// colored/annotated snippet rendering the rest of the compilation does.
+/// `PlainText` builds a dedicated, uncolored, snippet-free `Handler` over
+/// the same source map instead, for logs and other non-interactive output.
+pub fn emit_reports(compiler: &Compiler, reports: &[Report], format: DiagnosticFormat) {
+    match format {
+        DiagnosticFormat::Json => emit_json(reports),
+        DiagnosticFormat::Human => {
+            let handler = compiler.session().diagnostic();
+            for report in reports {
+                emit_one(handler, report);
+            }
+        }
+        DiagnosticFormat::PlainText => {
+            let handler = plain_text_handler(compiler);
+            for report in reports {
+                emit_one(&handler, report);
+            }
+        }
+    }
+}
+
+/// A `Handler` emitting uncolored, one-line-per-diagnostic text with no source
+/// snippet, for format `PlainText` — the same knobs rustc itself exposes
+/// via `--error-format=short` plus `--color=never`. `HumanReadableErrorType`
+/// is what actually controls the snippet: `Default` still renders the
+/// annotated source and carets `Human` does, just uncolored; `Short`
+/// collapses each diagnostic to its one-line `file:line:col: message` form
+/// at the primary span and does not print attached notes.
+fn plain_text_handler(compiler: &Compiler) -> Handler {
+    let source_map = compiler.session().parse_sess.clone_source_map();
+    let emitter: Box<dyn Emitter + Send> = Box::new(EmitterWriter::stderr(
+        ColorConfig::Never,
+        Some(source_map),
+        None,
+        HumanReadableErrorType::Short(false),
+        false,
+        false,
+        None,
+        false,
+    ));
+    Handler::with_emitter(true, None, emitter)
+}
+
+fn emit_one(handler: &Handler, report: &Report) {
+    let primary = report.primary_site();
+    let secondary = report.secondary_site();
+    handler
+        .struct_span_warn(primary.span, format!("{} detected", report.kind_str()))
+        .span_label(primary.span, "this acquisition may conflict with an earlier one")
+        .span_note(secondary.span, "first acquired here")
+        .emit();
+}
+
+fn emit_json(reports: &[Report]) {
+    for report in reports {
+        match serde_json::to_string(report) {
+            Ok(j) => println!("{j}"),
+            Err(e) => log::warn!("failed to serialize report as json: {e}"),
+        }
+    }
+}
@@ -0,0 +1,68 @@
+//! Command-line and programmatic configuration for a lockbud run.
+
+/// Which crates the detector should actually analyze.
+#[derive(Debug, Clone)]
+pub enum CrateNameList {
+    /// Only analyze the listed crates. An empty list disables the allowlist
+    /// (i.e. every crate is analyzed).
+    White(Vec<String>),
+    /// Analyze every crate except the listed ones.
+    Black(Vec<String>),
+}
+
+/// Which family of lock bugs to look for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DetectorKind {
+    Deadlock,
+}
+
+/// How a run's `Report`s should be rendered.
+///
+/// Mirrors rustc's own `ErrorOutputType`: the same findings can be shown as
+/// annotated source snippets for a human, as plain text for logs, or as JSON
+/// for tooling to consume.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiagnosticFormat {
+    /// Human-readable output with annotated source snippets, rendered by the
+    /// session's own handler (rustc's default `EmitterWriter` rendering).
+    Human,
+    /// Plain text: one line per report at its primary span, no color or
+    /// snippets; the secondary "first acquired here" note is not printed.
+    PlainText,
+    /// Machine-readable JSON, one object per `Report`, suitable for CI.
+    Json,
+}
+
+impl Default for DiagnosticFormat {
+    fn default() -> Self {
+        DiagnosticFormat::Human
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Options {
+    pub crate_name_list: CrateNameList,
+    pub detector_kind: DetectorKind,
+    /// How reports are emitted: annotated snippets, plain text, or JSON.
+    pub diagnostic_format: DiagnosticFormat,
+    /// How many threads to use for callgraph construction and lock
+    /// detection. `None` defers to rustc's own `-Z threads`/default thread
+    /// pool sizing. Only `api::analyze_str` applies it (as `-Z threads`);
+    /// `LockBudCallbacks` on the plugin path ignores it.
+    pub threads: Option<usize>,
+    /// Reuse per-function summaries from a previous run's sidecar cache
+    /// (under `output_directory`) instead of re-analyzing every function.
+    pub incremental: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            crate_name_list: CrateNameList::White(Vec::new()),
+            detector_kind: DetectorKind::Deadlock,
+            diagnostic_format: DiagnosticFormat::default(),
+            threads: None,
+            incremental: true,
+        }
+    }
+}
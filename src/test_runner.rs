@@ -0,0 +1,134 @@
+//! A small compiletest-style regression harness.
+//!
+//! Walks a directory of single-file fixture crates, drives each one through
+//! [`crate::api::analyze_str`] with codegen disabled, and checks the
+//! resulting `Vec<Report>` against expectations declared either inline
+//! (`//~` comments anchored to the offending line, same convention as
+//! rustc's own compiletest) or as a golden `<fixture>.expected.json` file
+//! sitting next to the fixture.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::api::{self, CrateType};
+use crate::detector::lock::Report;
+use crate::options::Options;
+
+/// An inline `//~ KIND possibility` expectation anchored to a source line.
+#[derive(Debug, PartialEq, Eq)]
+struct Expectation {
+    line: usize,
+    kind: &'static str,
+    possibility: String,
+}
+
+#[derive(Debug)]
+pub struct TestFailure {
+    pub fixture: PathBuf,
+    pub message: String,
+}
+
+/// Run every `*.rs` fixture directly under `dir`, returning one failure per
+/// fixture whose reports didn't match its expectations. An empty result
+/// means every fixture passed.
+pub fn run_dir(dir: &Path) -> Vec<TestFailure> {
+    let mut failures = Vec::new();
+    let Ok(entries) = fs::read_dir(dir) else {
+        return failures;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.extension().and_then(|e| e.to_str()) != Some("rs") {
+            continue;
+        }
+        if let Err(message) = run_fixture(&path) {
+            failures.push(TestFailure { fixture: path, message });
+        }
+    }
+    failures
+}
+
+fn run_fixture(path: &Path) -> Result<(), String> {
+    let source = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let reports = api::analyze_str(&source, CrateType::Lib, "2021", Options::default());
+
+    let golden = path.with_extension("expected.json");
+    if golden.exists() {
+        return check_golden(&golden, &reports);
+    }
+    check_inline(&source, &reports)
+}
+
+/// Normalize a report's spans to a `line:kind:possibility` triple so
+/// comparisons don't depend on the absolute path or column of a fixture.
+fn normalize(report: &Report) -> (usize, &'static str, String) {
+    // `LockSite::line` was resolved against the compiling session's own
+    // `SourceMap` back when the report was produced (see
+    // `detector::lock::site`); a fresh `SourceMap` here has no files
+    // registered, so re-deriving the line from `span` after the fact would
+    // both panic (on the `lookup_char_pos` bounds check) and be wrong even
+    // if it didn't.
+    let (site, kind, possibility) = match report {
+        Report::DoubleLock(r) => (&r.second_lock, "DOUBLELOCK", r.possibility.clone()),
+        Report::ConflictLock(r) => (&r.second_lock, "CONFLICTLOCK", r.possibility.clone()),
+    };
+    (site.line, kind, possibility)
+}
+
+fn parse_inline_expectations(source: &str) -> Vec<Expectation> {
+    let mut expectations = Vec::new();
+    for (idx, line) in source.lines().enumerate() {
+        let Some(pos) = line.find("//~") else { continue };
+        let rest = line[pos + 3..].trim();
+        let mut parts = rest.splitn(2, char::is_whitespace);
+        let kind = match parts.next() {
+            Some("DOUBLELOCK") => "DOUBLELOCK",
+            Some("CONFLICTLOCK") => "CONFLICTLOCK",
+            _ => continue,
+        };
+        let possibility = parts.next().unwrap_or("Possibly").trim().to_string();
+        expectations.push(Expectation { line: idx + 1, kind, possibility });
+    }
+    expectations
+}
+
+fn check_inline(source: &str, reports: &[Report]) -> Result<(), String> {
+    let expected = parse_inline_expectations(source);
+    let mut actual: Vec<(usize, &'static str, String)> =
+        reports.iter().map(normalize).collect();
+    for exp in &expected {
+        let pos = actual
+            .iter()
+            .position(|(line, kind, poss)| *line == exp.line && *kind == exp.kind && poss == &exp.possibility);
+        match pos {
+            Some(i) => {
+                actual.remove(i);
+            }
+            None => {
+                return Err(format!(
+                    "expected {} {} at line {}, but it was not reported",
+                    exp.kind, exp.possibility, exp.line
+                ))
+            }
+        }
+    }
+    if !actual.is_empty() {
+        return Err(format!("unexpected reports with no `//~` annotation: {actual:?}"));
+    }
+    Ok(())
+}
+
+fn check_golden(golden: &Path, reports: &[Report]) -> Result<(), String> {
+    let expected: Vec<Report> =
+        serde_json::from_str(&fs::read_to_string(golden).map_err(|e| e.to_string())?)
+            .map_err(|e| e.to_string())?;
+    let actual: Vec<_> = reports.iter().map(normalize).collect();
+    let expected: Vec<_> = expected.iter().map(normalize).collect();
+    if actual != expected {
+        return Err(format!(
+            "report mismatch against {}:\n  expected: {expected:?}\n  actual:   {actual:?}",
+            golden.display()
+        ));
+    }
+    Ok(())
+}
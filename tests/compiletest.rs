@@ -0,0 +1,14 @@
+//! Entry point for the fixture-based regression suite. See
+//! `src/test_runner.rs` for how each fixture is driven and checked.
+
+#[test]
+fn fixtures() {
+    let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+    let failures = lockbud::test_runner::run_dir(&dir);
+    if !failures.is_empty() {
+        for failure in &failures {
+            eprintln!("{}: {}", failure.fixture.display(), failure.message);
+        }
+        panic!("{} fixture(s) failed", failures.len());
+    }
+}
@@ -0,0 +1,14 @@
+// Fixture for the compiletest-style harness: two functions that acquire the
+// same two locks in opposite orders, which can deadlock if they run
+// concurrently against each other.
+use std::sync::Mutex;
+
+pub fn lock_a_then_b(a: &Mutex<i32>, b: &Mutex<i32>) {
+    let _ga = a.lock().unwrap();
+    let _gb = b.lock().unwrap();
+}
+
+pub fn lock_b_then_a(a: &Mutex<i32>, b: &Mutex<i32>) {
+    let _gb = b.lock().unwrap();
+    let _ga = a.lock().unwrap(); //~ CONFLICTLOCK Possibly
+}
@@ -0,0 +1,8 @@
+// Fixture for the compiletest-style harness: a function that acquires the
+// same `Mutex` twice without releasing it in between.
+use std::sync::Mutex;
+
+pub fn double_lock(m: &Mutex<i32>) {
+    let _g1 = m.lock().unwrap();
+    let _g2 = m.lock().unwrap(); //~ DOUBLELOCK Possibly
+}
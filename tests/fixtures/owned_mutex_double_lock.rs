@@ -0,0 +1,11 @@
+// Fixture for the compiletest-style harness: a double lock on an owned,
+// `Arc`-wrapped `Mutex`. Unlike `double_lock.rs`'s `&Mutex` parameter, each
+// `.lock()` call here goes through a fresh autoref temporary, which is the
+// shape `guard_sites`'s root-local resolution exists to see through.
+use std::sync::{Arc, Mutex};
+
+pub fn owned_mutex_double_lock() {
+    let m = Arc::new(Mutex::new(0));
+    let _g1 = m.lock().unwrap();
+    let _g2 = m.lock().unwrap(); //~ DOUBLELOCK Possibly
+}